@@ -3,6 +3,7 @@ use std::env;
 use std::fs::File;
 use std::io::{self, Read, Write};
 use std::path::Path;
+use std::process;
 
 use colored::Colorize;
 use regex::Regex;
@@ -13,7 +14,7 @@ pub const PKG_NAME: &str = env!("CARGO_PKG_NAME");
 
 /// Konfigurace ekvivalentní OptionParseru v Crystal kódu.
 /// Konfigurace ekvivalentní OptionParseru v Crystal kódu.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct TemplateConfig {
     pub file_name: Option<String>,
     pub rewrite: bool,
@@ -21,23 +22,19 @@ pub struct TemplateConfig {
     pub escape: bool,
     pub default: Option<String>,
     pub debug: bool,
-    /// Volitelná mapa proměnných prostředí – pokud je Some,
-    /// používá se místo skutečného process ENV.
-    pub env_vars: Option<HashMap<String, String>>,
-}
-
-impl Default for TemplateConfig {
-    fn default() -> Self {
-        TemplateConfig {
-            file_name: None,
-            rewrite: false,
-            helm_only: false,
-            escape: false,
-            default: None,
-            debug: false,
-            env_vars: None,
-        }
-    }
+    /// Mapy proměnných z `--env-file` v pořadí, v jakém byly zadány na
+    /// příkazové řádce; při vyhledávání se prochází od poslední k první,
+    /// takže pozdější soubor přebíjí dřívější (jako override vrstvy u
+    /// cargo/just).
+    pub env_files: Vec<HashMap<String, String>>,
+    /// Jednorázové `--set KEY=VALUE` override, mají nejvyšší prioritu.
+    pub overrides: HashMap<String, String>,
+    /// Potlačí fallback na process ENV, pokud proměnná není v `overrides`
+    /// ani v žádném `env_files`.
+    pub no_env: bool,
+    /// Jako `set -u` ve shellu: nevyřešený `{{VAR}}` bez `--if-not-found`
+    /// defaultu má skončit chybou místo tichého ponechání placeholderu.
+    pub strict: bool,
 }
 
 /// Port metody `escape_special_chars`
@@ -64,6 +61,26 @@ fn escape_special_chars(orig: &str) -> String {
     s
 }
 
+/// Aplikuje `--escape` (pokud je zapnuté) na vybranou hodnotu placeholderu
+/// a případně vypíše debug řádek - sdíleno mezi prostým `{{VAR}}`
+/// i operátory `:-`/`:+`/`:?`.
+fn apply_escape_and_debug(cfg: &TemplateConfig, i: usize, orig: &str, value: &str) -> String {
+    let v = if cfg.escape {
+        escape_special_chars(value)
+    } else {
+        value.to_string()
+    };
+    if cfg.debug {
+        println!(
+            "Found [{}], orig: \"{}\", apply with: \"{}\"",
+            i,
+            orig.yellow(),
+            v.green()
+        );
+    }
+    v
+}
+
 fn is_helm_wrapped(content: &str, start: usize, end: usize) -> bool {
     // `content[start..end]` je vnitřní "{{FOO}}"
     // chceme detekovat obal: {{` {{FOO}} `}}
@@ -74,7 +91,36 @@ fn is_helm_wrapped(content: &str, start: usize, end: usize) -> bool {
     &content[start - 3..start] == "{{`" && &content[end..end + 3] == "`}}"
 }
 
-pub fn render_template_with_lookup<F>(template: &str, cfg: &TemplateConfig, mut lookup: F) -> String
+/// Regex pro `{{VAR}}` / `{{VAR:-default}}` / `{{VAR:+alt}}` / `{{VAR:?msg}}`
+/// placeholdery - sdílený mezi `render_template_with_lookup` a
+/// `collect_variables`, aby obě místa rozpoznávala stejnou syntaxi.
+fn placeholder_regex() -> Regex {
+    Regex::new(r"(?ix)(\{\{\s*)(\w+)(?:\s*(:[-+?])\s*([^}]*))?(\s*\}\})")
+        .expect("invalid regex for placeholder scanning")
+}
+
+/// Projde šablonu a vrátí jména všech `{{VAR}}` placeholderů, seřazená
+/// a bez duplicit - obdoba `just --variables` / `just --dump`, jen pro
+/// tento templating jazyk.
+pub fn collect_variables(template: &str) -> Vec<String> {
+    let re = placeholder_regex();
+    let names: std::collections::BTreeSet<String> = re
+        .captures_iter(template)
+        .map(|caps| caps.get(2).unwrap().as_str().to_string())
+        .collect();
+    names.into_iter().collect()
+}
+
+/// Vykreslí šablonu a zároveň sbírá jména `{{VAR}}` placeholderů, pro
+/// které `lookup` vrátil `None` a neexistuje `--if-not-found` default
+/// (v pořadí prvního výskytu, bez duplicit). `missing` slouží volajícímu
+/// (viz `--strict` v `run_from_stdio`) k rozhodnutí, zda výstup přijmout.
+pub fn render_template_with_lookup<F>(
+    template: &str,
+    cfg: &TemplateConfig,
+    mut lookup: F,
+    missing: &mut Vec<String>,
+) -> String
 where
     F: FnMut(&str) -> Option<String>,
 {
@@ -82,8 +128,7 @@ where
         return String::new();
     }
 
-    let re = Regex::new(r"(?ix)(\{\{\s*)(\w+)(\s*\}\})")
-        .expect("invalid regex for render_template_with_lookup");
+    let re = placeholder_regex();
 
     let mut result = String::with_capacity(template.len());
     let mut last_end = 0usize;
@@ -97,6 +142,8 @@ where
 
         let orig = m.as_str();
         let name = caps.get(2).unwrap().as_str();
+        let operator = caps.get(3).map(|m| m.as_str());
+        let word = caps.get(4).map(|m| m.as_str()).unwrap_or("");
 
         let replacement = if cfg.helm_only {
             if is_helm_wrapped(template, start, end) {
@@ -114,45 +161,62 @@ where
                 val
             }
         } else {
-            match lookup(name) {
-                Some(v) => {
-                    let v2 = if cfg.escape {
-                        escape_special_chars(&v)
+            let looked_up = lookup(name);
+            let set_and_non_empty = matches!(looked_up.as_deref(), Some(v) if !v.is_empty());
+
+            match operator {
+                Some(":-") => {
+                    let v = if set_and_non_empty {
+                        looked_up.unwrap()
                     } else {
-                        v
+                        word.to_string()
                     };
-                    if cfg.debug {
-                        println!(
-                            "Found [{}], orig: \"{}\", apply with: \"{}\"",
-                            i,
-                            orig.yellow(),
-                            v2.green()
-                        );
-                    }
-                    v2
+                    apply_escape_and_debug(cfg, i, orig, &v)
                 }
-                None => {
-                    if let Some(ref default) = cfg.default {
-                        if cfg.debug {
-                            println!(
-                                "Found [{}], orig: \"{}\", apply with default: \"{}\"",
-                                i,
-                                orig.yellow(),
-                                default.green()
-                            );
-                        }
-                        default.clone()
+                Some(":+") => {
+                    let v = if set_and_non_empty {
+                        word.to_string()
                     } else {
-                        if cfg.debug {
-                            println!(
-                                "Found [{}], orig: \"{}\", not found and no default, keeping as-is",
-                                i,
-                                orig.yellow()
-                            );
-                        }
-                        orig.to_string()
+                        String::new()
+                    };
+                    apply_escape_and_debug(cfg, i, orig, &v)
+                }
+                Some(":?") => {
+                    if set_and_non_empty {
+                        apply_escape_and_debug(cfg, i, orig, &looked_up.unwrap())
+                    } else {
+                        eprintln!("{}: {}", name, word);
+                        process::exit(1);
                     }
                 }
+                _ => match looked_up {
+                    Some(v) => apply_escape_and_debug(cfg, i, orig, &v),
+                    None => {
+                        if let Some(ref default) = cfg.default {
+                            if cfg.debug {
+                                println!(
+                                    "Found [{}], orig: \"{}\", apply with default: \"{}\"",
+                                    i,
+                                    orig.yellow(),
+                                    default.green()
+                                );
+                            }
+                            default.clone()
+                        } else {
+                            if cfg.debug {
+                                println!(
+                                    "Found [{}], orig: \"{}\", not found and no default, keeping as-is",
+                                    i,
+                                    orig.yellow()
+                                );
+                            }
+                            if !missing.iter().any(|m| m == name) {
+                                missing.push(name.to_string());
+                            }
+                            orig.to_string()
+                        }
+                    }
+                },
             }
         };
 
@@ -166,23 +230,120 @@ where
 }
 
 /// Čistá funkce pro templating - obdoba `Template#render`
-/// bez I/O (užitečné pro testy a embedování).
-pub fn render_template_str(template: &str, cfg: &TemplateConfig) -> String {
-    if let Some(ref map) = cfg.env_vars {
-        // Použijeme pouze hodnoty z mapy (např. načtené z .env souboru)
-        render_template_with_lookup(template, cfg, |name| map.get(name).cloned())
+/// bez I/O (užitečné pro testy a embedování). `missing` se naplní jmény
+/// nevyřešených placeholderů, viz `render_template_with_lookup`.
+pub fn render_template_str(template: &str, cfg: &TemplateConfig, missing: &mut Vec<String>) -> String {
+    render_template_with_lookup(template, cfg, |name| resolve_var(cfg, name), missing)
+}
+
+/// Vyřeší jméno proměnné podle vrstvené priority popsané u
+/// `TemplateConfig`: `--set` override, pak `--env-file` mapy od poslední
+/// k první, nakonec process ENV (pokud není `--no-env`).
+fn resolve_var(cfg: &TemplateConfig, name: &str) -> Option<String> {
+    if let Some(v) = cfg.overrides.get(name) {
+        return Some(v.clone());
+    }
+
+    for map in cfg.env_files.iter().rev() {
+        if let Some(v) = map.get(name) {
+            return Some(v.clone());
+        }
+    }
+
+    if cfg.no_env {
+        None
     } else {
-        // Výchozí chování: čteme z process ENV
-        render_template_with_lookup(template, cfg, |name| env::var(name).ok())
+        env::var(name).ok()
     }
 }
 
+/// Stav rozřešení jednoho placeholderu pro `--list` / `audit_variables`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariableStatus {
+    pub name: String,
+    /// `"resolved"`, `"default"` nebo `"missing"`.
+    pub status: String,
+    pub value: Option<String>,
+}
+
+/// Pro každý placeholder z `collect_variables` zjistí, jestli je
+/// rozřešitelný z vrstvených zdrojů proměnných (`--set`, `--env-file` mapy,
+/// process ENV pokud není `--no-env`; viz `resolve_var`), spadne na
+/// `--if-not-found` default, nebo zůstane
+/// nevyřešený. Používá `--list`, aby šlo v CI ověřit úplnost prostředí
+/// před samotným templatingem.
+pub fn audit_variables(template: &str, cfg: &TemplateConfig) -> Vec<VariableStatus> {
+    collect_variables(template)
+        .into_iter()
+        .map(|name| {
+            let looked_up = resolve_var(cfg, &name);
+
+            match looked_up {
+                Some(value) => VariableStatus {
+                    name,
+                    status: "resolved".to_string(),
+                    value: Some(value),
+                },
+                None => match &cfg.default {
+                    Some(default) => VariableStatus {
+                        name,
+                        status: "default".to_string(),
+                        value: Some(default.clone()),
+                    },
+                    None => VariableStatus {
+                        name,
+                        status: "missing".to_string(),
+                        value: None,
+                    },
+                },
+            }
+        })
+        .collect()
+}
+
+/// Obdoba `run_from_stdio`, jen místo vykreslení šablony vypíše report
+/// o tom, jaké proměnné šablona referencuje a jak by se rozřešily.
+pub fn run_list_mode(cfg: &TemplateConfig, format: &str) -> io::Result<()> {
+    let content = load_content(cfg)?;
+    let statuses = audit_variables(&content, cfg);
+
+    if format.eq_ignore_ascii_case("json") {
+        let items: Vec<serde_json::Value> = statuses
+            .iter()
+            .map(|s| {
+                serde_json::json!({
+                    "name": s.name,
+                    "status": s.status,
+                    "value": s.value,
+                })
+            })
+            .collect();
+        let rendered = serde_json::to_string_pretty(&items).map_err(io::Error::other)?;
+        println!("{}", rendered);
+    } else {
+        for s in &statuses {
+            println!("{} {}", s.name, s.status);
+        }
+    }
+
+    Ok(())
+}
+
 /// Port `Template#load_content` + `Template#rewrite?`
 /// používané `main` funkcí - čte ze stdin/souboru a
 /// buď vypíše, nebo přepíše soubor.
 pub fn run_from_stdio(cfg: TemplateConfig) -> io::Result<()> {
     let content = load_content(&cfg)?;
-    let rendered = render_template_str(&content, &cfg);
+    let mut missing = Vec::new();
+    let rendered = render_template_str(&content, &cfg, &mut missing);
+
+    if cfg.strict && !missing.is_empty() {
+        for name in &missing {
+            eprintln!("missing variable: {}", name);
+        }
+        process::exit(1);
+    }
+
     rewrite_or_print(&rendered, &cfg)
 }
 
@@ -246,7 +407,7 @@ mod tests {
     where
         F: FnMut(&str) -> Option<String>,
     {
-        render_template_with_lookup(template, cfg, f)
+        render_template_with_lookup(template, cfg, f, &mut Vec::new())
     }
 
     #[test]
@@ -292,7 +453,7 @@ mod tests {
             ..Default::default()
         };
 
-        let rendered = render_template_str("{{FOO}}", &cfg);
+        let rendered = render_template_str("{{FOO}}", &cfg, &mut Vec::new());
         assert_eq!(rendered, "{{`{{FOO}}`}}");
     }
 
@@ -303,7 +464,7 @@ mod tests {
             ..Default::default()
         };
 
-        let rendered = render_template_str("hello: {{`{{FOO}}`}}", &cfg);
+        let rendered = render_template_str("hello: {{`{{FOO}}`}}", &cfg, &mut Vec::new());
         assert_eq!(rendered, "hello: {{`{{FOO}}`}}");
     }
 
@@ -313,7 +474,7 @@ mod tests {
             helm_only: true,
             ..Default::default()
         };
-        let rendered = render_template_str(r#"hello: "{{FOO}} -> {{FOO}} -> {{FOO}}""#, &cfg);
+        let rendered = render_template_str(r#"hello: "{{FOO}} -> {{FOO}} -> {{FOO}}""#, &cfg, &mut Vec::new());
         assert_eq!(
             rendered,
             r#"hello: "{{`{{FOO}}`}} -> {{`{{FOO}}`}} -> {{`{{FOO}}`}}""#
@@ -353,18 +514,213 @@ mod tests {
     }
 
     #[test]
-    fn uses_env_vars_map_instead_of_process_env() {
+    fn uses_env_file_map_instead_of_process_env() {
         use std::collections::HashMap;
 
         let mut map = HashMap::new();
         map.insert("FOO".to_string(), "from_file".to_string());
 
         let cfg = TemplateConfig {
-            env_vars: Some(map),
+            env_files: vec![map],
             ..Default::default()
         };
 
-        let rendered = render_template_str("x {{FOO}} y", &cfg);
+        let rendered = render_template_str("x {{FOO}} y", &cfg, &mut Vec::new());
         assert_eq!(rendered, "x from_file y");
     }
+
+    #[test]
+    fn default_operator_used_when_unset() {
+        let cfg = TemplateConfig::default();
+
+        let rendered = render_with_fake_env("x {{FOO:-fallback}} y", &cfg, |_name| None);
+        assert_eq!(rendered, "x fallback y");
+    }
+
+    #[test]
+    fn default_operator_used_when_empty() {
+        let cfg = TemplateConfig::default();
+
+        let rendered = render_with_fake_env("x {{FOO:-fallback}} y", &cfg, |name| match name {
+            "FOO" => Some(String::new()),
+            _ => None,
+        });
+        assert_eq!(rendered, "x fallback y");
+    }
+
+    #[test]
+    fn default_operator_not_used_when_set() {
+        let cfg = TemplateConfig::default();
+
+        let rendered = render_with_fake_env("x {{FOO:-fallback}} y", &cfg, |name| match name {
+            "FOO" => Some("bar".to_string()),
+            _ => None,
+        });
+        assert_eq!(rendered, "x bar y");
+    }
+
+    #[test]
+    fn alt_operator_used_only_when_set_and_non_empty() {
+        let cfg = TemplateConfig::default();
+
+        let rendered_unset = render_with_fake_env("x {{FOO:+alt}} y", &cfg, |_name| None);
+        assert_eq!(rendered_unset, "x  y");
+
+        let rendered_set = render_with_fake_env("x {{FOO:+alt}} y", &cfg, |name| match name {
+            "FOO" => Some("bar".to_string()),
+            _ => None,
+        });
+        assert_eq!(rendered_set, "x alt y");
+    }
+
+    #[test]
+    fn required_operator_passes_through_value_when_set() {
+        let cfg = TemplateConfig::default();
+
+        let rendered = render_with_fake_env(
+            "x {{FOO:?FOO is required}} y",
+            &cfg,
+            |name| match name {
+                "FOO" => Some("bar".to_string()),
+                _ => None,
+            },
+        );
+        assert_eq!(rendered, "x bar y");
+    }
+
+    #[test]
+    fn collects_distinct_missing_variables_in_first_seen_order() {
+        let cfg = TemplateConfig::default();
+        let mut missing = Vec::new();
+
+        let rendered = render_template_with_lookup(
+            "{{FOO}} {{BAR}} {{FOO}}",
+            &cfg,
+            |_name| None,
+            &mut missing,
+        );
+
+        assert_eq!(rendered, "{{FOO}} {{BAR}} {{FOO}}");
+        assert_eq!(missing, vec!["FOO".to_string(), "BAR".to_string()]);
+    }
+
+    #[test]
+    fn does_not_collect_missing_when_default_present() {
+        let cfg = TemplateConfig {
+            default: Some("42".to_string()),
+            ..Default::default()
+        };
+        let mut missing = Vec::new();
+
+        render_template_with_lookup("{{FOO}}", &cfg, |_name| None, &mut missing);
+
+        assert!(missing.is_empty());
+    }
+
+    #[test]
+    fn collect_variables_is_sorted_and_deduplicated() {
+        let names = collect_variables("{{BAR}} {{FOO}} {{BAR}} {{FOO:-x}}");
+        assert_eq!(names, vec!["BAR".to_string(), "FOO".to_string()]);
+    }
+
+    #[test]
+    fn audit_variables_reports_resolved_and_missing() {
+        let mut map = HashMap::new();
+        map.insert("FOO".to_string(), "bar".to_string());
+
+        let cfg = TemplateConfig {
+            env_files: vec![map],
+            ..Default::default()
+        };
+
+        let mut statuses = audit_variables("{{FOO}} {{MISSING}}", &cfg);
+        statuses.sort_by(|a, b| a.name.cmp(&b.name));
+
+        assert_eq!(
+            statuses,
+            vec![
+                VariableStatus {
+                    name: "FOO".to_string(),
+                    status: "resolved".to_string(),
+                    value: Some("bar".to_string()),
+                },
+                VariableStatus {
+                    name: "MISSING".to_string(),
+                    status: "missing".to_string(),
+                    value: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn audit_variables_reports_default_when_if_not_found_is_set() {
+        let cfg = TemplateConfig {
+            default: Some("fallback".to_string()),
+            ..Default::default()
+        };
+
+        let statuses = audit_variables("{{DEFAULTED}}", &cfg);
+
+        assert_eq!(
+            statuses,
+            vec![VariableStatus {
+                name: "DEFAULTED".to_string(),
+                status: "default".to_string(),
+                value: Some("fallback".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn later_env_file_wins_over_earlier_one() {
+        let mut first = HashMap::new();
+        first.insert("FOO".to_string(), "from_first".to_string());
+        let mut second = HashMap::new();
+        second.insert("FOO".to_string(), "from_second".to_string());
+
+        let cfg = TemplateConfig {
+            env_files: vec![first, second],
+            ..Default::default()
+        };
+
+        let rendered = render_template_str("{{FOO}}", &cfg, &mut Vec::new());
+        assert_eq!(rendered, "from_second");
+    }
+
+    #[test]
+    fn set_override_wins_over_env_files() {
+        let mut map = HashMap::new();
+        map.insert("FOO".to_string(), "from_file".to_string());
+
+        let mut overrides = HashMap::new();
+        overrides.insert("FOO".to_string(), "from_set".to_string());
+
+        let cfg = TemplateConfig {
+            env_files: vec![map],
+            overrides,
+            ..Default::default()
+        };
+
+        let rendered = render_template_str("{{FOO}}", &cfg, &mut Vec::new());
+        assert_eq!(rendered, "from_set");
+    }
+
+    #[test]
+    fn no_env_suppresses_process_env_fallback() {
+        let cfg = TemplateConfig {
+            no_env: true,
+            ..Default::default()
+        };
+
+        unsafe {
+            std::env::set_var("APPLY_ENV_RS_TEST_NO_ENV", "from_process_env");
+        }
+        let rendered = render_template_str("{{APPLY_ENV_RS_TEST_NO_ENV}}", &cfg, &mut Vec::new());
+        unsafe {
+            std::env::remove_var("APPLY_ENV_RS_TEST_NO_ENV");
+        }
+
+        assert_eq!(rendered, "{{APPLY_ENV_RS_TEST_NO_ENV}}");
+    }
 }
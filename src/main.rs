@@ -1,10 +1,12 @@
 use std::collections::HashMap;
 use std::env;
 use std::fs;
+use std::io;
 use std::process;
 
-use apply_env::{TemplateConfig, run_from_stdio};
+use apply_env::{TemplateConfig, run_from_stdio, run_list_mode};
 use clap::{CommandFactory, Parser};
+use clap_complete::Shell;
 
 /// Apply environment variables to templates (Rust port of apply-env).
 #[derive(Parser, Debug)]
@@ -40,9 +42,33 @@ struct Cli {
     #[arg(short = 'd', long = "debug")]
     debug: bool,
 
-    /// Load variables from a .env-style file (instead of process ENV)
+    /// Load variables from a .env-style file (repeatable, later files win)
     #[arg(short = 'E', long = "env-file", value_name = "FILE")]
-    env_file: Option<String>,
+    env_file: Vec<String>,
+
+    /// Set a variable inline as KEY=VALUE (repeatable, highest precedence)
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    set: Vec<String>,
+
+    /// Do not fall back to the process environment
+    #[arg(long = "no-env")]
+    no_env: bool,
+
+    /// Fail (exit 1) if any {{VAR}} has no value and no --if-not-found default
+    #[arg(short = 's', long = "strict")]
+    strict: bool,
+
+    /// List referenced variables and their resolution status instead of rendering
+    #[arg(short = 'l', long = "list")]
+    list: bool,
+
+    /// Output format for --list: "text" (default) or "json"
+    #[arg(long = "format", value_name = "FORMAT", default_value = "text")]
+    format: String,
+
+    /// Generate a shell completion script to stdout and exit
+    #[arg(long = "completions", value_name = "SHELL")]
+    completions: Option<Shell>,
 }
 
 fn normalize_args(raw_args: Vec<String>) -> Vec<String> {
@@ -102,6 +128,13 @@ fn main() {
     // 3) Necháme clap zparsovat argumenty (včetně -h / -v)
     let cli = Cli::parse_from(args_for_clap);
 
+    // 3b) --completions SHELL: vygeneruj completion skript a skonči,
+    // ještě před kontrolou -f/stdin (packageři ho volají bez šablony).
+    if let Some(shell) = cli.completions {
+        clap_complete::generate(shell, &mut Cli::command(), "apply-env", &mut io::stdout());
+        return;
+    }
+
     // 4) Musí být nějaký zdroj vstupu, tj. -f NAME nebo alias "-"
     if cli.file.is_none() {
         let mut cmd = Cli::command();
@@ -110,17 +143,32 @@ fn main() {
         process::exit(1);
     }
 
-    // 5) env-file (pokud je)
-    let env_vars = match cli.env_file {
-        Some(path) => match load_env_file(&path) {
-            Ok(map) => Some(map),
+    // 5) --env-file (v zadaném pořadí, pozdější přebíjí dřívější)
+    let env_files: Vec<HashMap<String, String>> = cli
+        .env_file
+        .iter()
+        .map(|path| match load_env_file(path) {
+            Ok(map) => map,
             Err(err) => {
                 eprintln!("ERROR: failed to read env file {path}: {err}");
                 process::exit(1);
             }
-        },
-        None => None,
-    };
+        })
+        .collect();
+
+    // 5b) --set KEY=VALUE (nejvyšší priorita)
+    let mut overrides = HashMap::new();
+    for entry in &cli.set {
+        match parse_kv_line(entry) {
+            Some((key, value, _single_quoted)) => {
+                overrides.insert(key, value);
+            }
+            None => {
+                eprintln!("ERROR: invalid --set entry: {entry}");
+                process::exit(1);
+            }
+        }
+    }
 
     // 6) Config pro core logiku
     let cfg = TemplateConfig {
@@ -130,10 +178,22 @@ fn main() {
         escape: cli.escape,
         default: cli.if_not_found,
         debug: cli.debug,
-        env_vars,
+        env_files,
+        overrides,
+        no_env: cli.no_env,
+        strict: cli.strict,
     };
 
-    // 7) Templating (stdin / soubor podle file_name)
+    // 7) --list: vypiš report o referencovaných proměnných a skonči
+    if cli.list {
+        if let Err(err) = run_list_mode(&cfg, &cli.format) {
+            eprintln!("ERROR: {err}");
+            process::exit(1);
+        }
+        return;
+    }
+
+    // 8) Templating (stdin / soubor podle file_name)
     if let Err(err) = run_from_stdio(cfg) {
         eprintln!("ERROR: {err}");
         process::exit(1);
@@ -157,13 +217,9 @@ fn load_env_file(path: &str) -> std::io::Result<HashMap<String, String>> {
             continue;
         }
 
-        // Volitelně stripneme "export "
-        let without_export = trimmed.strip_prefix("export ").unwrap_or(trimmed);
-
-        let mut parts = without_export.splitn(2, '=');
-        let key = match parts.next() {
-            Some(k) if !k.trim().is_empty() => k.trim(),
-            _ => {
+        let (key, value_unquoted, single_quoted) = match parse_kv_line(trimmed) {
+            Some(parsed) => parsed,
+            None => {
                 // Nekorektní řádek, prostě přeskočíme
                 eprintln!(
                     "WARNING: ignoring malformed line {} in env file {}",
@@ -174,23 +230,103 @@ fn load_env_file(path: &str) -> std::io::Result<HashMap<String, String>> {
             }
         };
 
-        let value_raw = parts.next().unwrap_or("").trim();
-
-        // Podpora jednoduchých a dvojitých uvozovek kolem hodnoty
-        let value_unquoted = if (value_raw.starts_with('"') && value_raw.ends_with('"'))
-            || (value_raw.starts_with('\'') && value_raw.ends_with('\''))
-        {
-            &value_raw[1..value_raw.len() - 1]
+        // Jednoduché uvozovky berou hodnotu doslovně (žádná interpolace),
+        // stejně jako v shellu.
+        let value = if single_quoted {
+            value_unquoted
         } else {
-            value_raw
+            interpolate_env_value(&value_unquoted, &map)
         };
 
-        map.insert(key.to_string(), value_unquoted.to_string());
+        map.insert(key, value);
     }
 
     Ok(map)
 }
 
+/// Rozparsuje jeden řádek tvaru `[export ]KEY=VALUE`, sejme volitelné
+/// uvozovky kolem hodnoty a řekne, zda šlo o jednoduché (`'...'`, tedy
+/// doslovné) nebo dvojité/žádné uvozovky. Sdíleno mezi `load_env_file`
+/// (řádek ze souboru) a `--set KEY=VALUE` na příkazové řádce.
+fn parse_kv_line(line: &str) -> Option<(String, String, bool)> {
+    // Volitelně stripneme "export "
+    let without_export = line.strip_prefix("export ").unwrap_or(line);
+
+    let mut parts = without_export.splitn(2, '=');
+    let key = match parts.next() {
+        Some(k) if !k.trim().is_empty() => k.trim(),
+        _ => return None,
+    };
+
+    let value_raw = parts.next().unwrap_or("").trim();
+
+    let (value_unquoted, single_quoted) = if value_raw.len() >= 2
+        && value_raw.starts_with('\'')
+        && value_raw.ends_with('\'')
+    {
+        (&value_raw[1..value_raw.len() - 1], true)
+    } else if value_raw.len() >= 2 && value_raw.starts_with('"') && value_raw.ends_with('"') {
+        (&value_raw[1..value_raw.len() - 1], false)
+    } else {
+        (value_raw, false)
+    };
+
+    Some((key.to_string(), value_unquoted.to_string(), single_quoted))
+}
+
+/// Dosadí `${NAME}` a holé `$NAME` reference uvnitř hodnoty z .env souboru,
+/// stejně jako by to udělal shell při expanzi proměnných: nejdřív se hledá
+/// `name` mezi už načtenými klíči ze stejného souboru, pak v process ENV,
+/// jinak se dosadí prázdný řetězec. `\$` je literální dolar.
+fn interpolate_env_value(value: &str, already_loaded: &HashMap<String, String>) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    let mut result = String::with_capacity(value.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c == '\\' && i + 1 < chars.len() && chars[i + 1] == '$' {
+            result.push('$');
+            i += 2;
+            continue;
+        }
+
+        if c == '$' && i + 1 < chars.len() && chars[i + 1] == '{' {
+            if let Some(rel_close) = chars[i + 2..].iter().position(|&c| c == '}') {
+                let name: String = chars[i + 2..i + 2 + rel_close].iter().collect();
+                result.push_str(&resolve_interpolated_var(&name, already_loaded));
+                i += 2 + rel_close + 1;
+                continue;
+            }
+        }
+
+        if c == '$' && i + 1 < chars.len() && (chars[i + 1].is_alphabetic() || chars[i + 1] == '_') {
+            let mut j = i + 1;
+            while j < chars.len() && (chars[j].is_alphanumeric() || chars[j] == '_') {
+                j += 1;
+            }
+            let name: String = chars[i + 1..j].iter().collect();
+            result.push_str(&resolve_interpolated_var(&name, already_loaded));
+            i = j;
+            continue;
+        }
+
+        result.push(c);
+        i += 1;
+    }
+
+    result
+}
+
+fn resolve_interpolated_var(name: &str, already_loaded: &HashMap<String, String>) -> String {
+    already_loaded
+        .get(name)
+        .cloned()
+        .or_else(|| env::var(name).ok())
+        .unwrap_or_default()
+}
+
 #[cfg(test)]
 mod cli_tests {
     use super::*;
@@ -236,4 +372,108 @@ mod cli_tests {
         let cli = Cli::parse_from(normalized);
         assert_eq!(cli.file.as_deref(), Some("template.yaml"));
     }
+
+    #[test]
+    fn repeated_env_file_and_set_are_collected_in_order() {
+        let args = vec![
+            "apply-env".to_string(),
+            "-f".to_string(),
+            "template.yaml".to_string(),
+            "-E".to_string(),
+            "base.env".to_string(),
+            "-E".to_string(),
+            "override.env".to_string(),
+            "--set".to_string(),
+            "FOO=bar".to_string(),
+            "--set".to_string(),
+            "BAZ=qux".to_string(),
+            "--no-env".to_string(),
+        ];
+
+        let cli = Cli::parse_from(args);
+        assert_eq!(cli.env_file, vec!["base.env", "override.env"]);
+        assert_eq!(cli.set, vec!["FOO=bar", "BAZ=qux"]);
+        assert!(cli.no_env);
+    }
+
+    #[test]
+    fn completions_flag_is_parsed_without_a_file() {
+        // Simuluje: apply-env --completions zsh (bez -f)
+        let args = vec![
+            "apply-env".to_string(),
+            "--completions".to_string(),
+            "zsh".to_string(),
+        ];
+
+        let cli = Cli::parse_from(args);
+        assert_eq!(cli.completions, Some(Shell::Zsh));
+        assert!(cli.file.is_none());
+    }
+}
+
+#[cfg(test)]
+mod env_file_tests {
+    use super::*;
+    use std::io::Write;
+
+    fn write_temp_env_file(name: &str, content: &str) -> String {
+        let path = std::env::temp_dir().join(format!("apply-env-test-{}.env", name));
+        let mut file = fs::File::create(&path).expect("failed to create temp env file");
+        file.write_all(content.as_bytes())
+            .expect("failed to write temp env file");
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn chained_references_are_expanded() {
+        let path = write_temp_env_file("chained", "A=1\nB=${A}2\n");
+
+        let map = load_env_file(&path).expect("failed to load env file");
+        fs::remove_file(&path).ok();
+
+        assert_eq!(map.get("B").map(String::as_str), Some("12"));
+    }
+
+    #[test]
+    fn undefined_references_expand_to_empty() {
+        let path = write_temp_env_file("undefined", "B=${NOPE_NOT_SET}2\n");
+
+        let map = load_env_file(&path).expect("failed to load env file");
+        fs::remove_file(&path).ok();
+
+        assert_eq!(map.get("B").map(String::as_str), Some("2"));
+    }
+
+    #[test]
+    fn escaped_dollar_is_literal() {
+        let path = write_temp_env_file("escaped", r#"A=1\$A"#);
+
+        let map = load_env_file(&path).expect("failed to load env file");
+        fs::remove_file(&path).ok();
+
+        assert_eq!(map.get("A").map(String::as_str), Some("1$A"));
+    }
+
+    #[test]
+    fn single_quoted_values_are_literal() {
+        let path = write_temp_env_file("single_quoted", "A=1\nB='${A}2'\n");
+
+        let map = load_env_file(&path).expect("failed to load env file");
+        fs::remove_file(&path).ok();
+
+        assert_eq!(map.get("B").map(String::as_str), Some("${A}2"));
+    }
+
+    #[test]
+    fn parse_kv_line_strips_quotes_for_set_entries() {
+        assert_eq!(
+            parse_kv_line("FOO=bar"),
+            Some(("FOO".to_string(), "bar".to_string(), false))
+        );
+        assert_eq!(
+            parse_kv_line("FOO='bar'"),
+            Some(("FOO".to_string(), "bar".to_string(), true))
+        );
+        assert_eq!(parse_kv_line("=bar"), None);
+    }
 }